@@ -482,6 +482,7 @@ impl EasyVersionApp {
                                 })
                                 .await
                                 .unwrap()
+                                .map(|_| ())
                                 .map_err(|e| e.to_string())
                         },
                         Message::SaveComplete,
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -16,7 +19,30 @@ pub struct Id {
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Manifest {
     /// The mathematical mapping from a relative path to the derived identity of its content.
+    #[serde(with = "path_map")]
     pub files: HashMap<PathBuf, Id>,
+    /// The relative paths of directories without any entries, which no file path would recreate.
+    #[serde(default, with = "path_set")]
+    pub empty_directories: HashSet<PathBuf>,
+    /// The Unix permission bits of each file, keyed by relative path. Empty for manifests captured on
+    /// other platforms or before permissions were recorded.
+    #[serde(default, with = "path_map")]
+    pub modes: HashMap<PathBuf, u32>,
+}
+
+/// The boundary of a directory walk, limiting which entries of a directory a snapshot captures.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct Scope {
+    /// The deepest level of subdirectories to descend into, where 0 captures only the files directly
+    /// within the directory. Absent for an unlimited descent.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Glob patterns naming entries to leave out, matched against paths relative to the directory.
+    /// `*` and `?` match within a single component, `**` matches any number of components, and a
+    /// trailing `/` restricts a pattern to directories. Patterns without any other `/` match at
+    /// any depth, while the rest are anchored to the directory.
+    #[serde(default)]
+    pub ignore: Vec<String>,
 }
 
 /// An immutable point-in-time capture of a directory's state.
@@ -25,6 +51,16 @@ pub struct Manifest {
 pub struct Snapshot {
     /// An optional, arbitrary user-defined string describing the snapshot context.
     pub comment: Option<String>,
+    /// The moment the snapshot was captured, in seconds since the Unix epoch.
+    /// Absent for snapshots recorded before timestamps were introduced.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// The name of whoever recorded the snapshot, if one was given.
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The boundary of the walk that produced the manifest; entries beyond it were never captured.
+    #[serde(default)]
+    pub scope: Scope,
     /// The pure structural state captured by this snapshot.
     pub manifest: Manifest,
 }
@@ -36,3 +72,426 @@ pub struct History {
     /// The chronologically ordered series of state captures.
     pub snapshots: Vec<Snapshot>,
 }
+
+/// The structural delta between two manifests, expressed as sorted sets of relative paths.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ManifestDiff {
+    /// File paths present only in the newer manifest.
+    pub added: Vec<PathBuf>,
+    /// File paths present only in the older manifest.
+    pub removed: Vec<PathBuf>,
+    /// File paths present in both manifests whose content identities differ.
+    pub modified: Vec<PathBuf>,
+    /// Empty directories present only in the newer manifest.
+    pub added_directories: Vec<PathBuf>,
+    /// Empty directories present only in the older manifest.
+    pub removed_directories: Vec<PathBuf>,
+}
+
+impl Manifest {
+    /// Computes the file and empty-directory changes required to move from this manifest to `newer`.
+    /// Changes to permission bits alone are not reported.
+    pub fn diff(&self, newer: &Manifest) -> ManifestDiff {
+        let mut diff = ManifestDiff::default();
+        for (path, id) in &newer.files {
+            match self.files.get(path) {
+                Some(old_id) if old_id != id => diff.modified.push(path.clone()),
+                None => diff.added.push(path.clone()),
+                _ => {}
+            }
+        }
+        for path in self.files.keys() {
+            if !newer.files.contains_key(path) {
+                diff.removed.push(path.clone());
+            }
+        }
+
+        diff.added_directories = newer
+            .empty_directories
+            .difference(&self.empty_directories)
+            .cloned()
+            .collect();
+        diff.removed_directories = self
+            .empty_directories
+            .difference(&newer.empty_directories)
+            .cloned()
+            .collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort();
+        diff.added_directories.sort();
+        diff.removed_directories.sort();
+        diff
+    }
+}
+
+impl Scope {
+    /// Determines whether an entry at `rel_path`, relative to the scoped directory, matches any of
+    /// the ignore patterns. Directory-only patterns never match entries that are not directories.
+    pub fn ignores(&self, rel_path: &Path, is_dir: bool) -> bool {
+        let components: Vec<String> = rel_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let components: Vec<&str> = components.iter().map(String::as_str).collect();
+
+        self.ignore.iter().any(|pattern| {
+            let (pattern, directory_only) = match pattern.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (pattern.as_str(), false),
+            };
+            if directory_only && !is_dir {
+                return false;
+            }
+
+            let mut segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+            if !pattern.contains('/') {
+                segments.insert(0, "**");
+            }
+            !segments.is_empty() && segments_match(&segments, &components)
+        })
+    }
+}
+
+impl History {
+    /// Finds every snapshot whose comment contains `needle`, matched case-sensitively.
+    /// Returns each match alongside its 0-indexed position, preserving chronological order.
+    /// An empty needle matches every snapshot that carries a comment.
+    pub fn find_by_comment(&self, needle: &str) -> Vec<(usize, &Snapshot)> {
+        self.snapshots
+            .iter()
+            .enumerate()
+            .filter(|(_, snapshot)| {
+                snapshot
+                    .comment
+                    .as_deref()
+                    .is_some_and(|comment| comment.contains(needle))
+            })
+            .collect()
+    }
+
+    /// Finds every snapshot recorded by exactly the author `name`.
+    /// Returns each match alongside its 0-indexed position, preserving chronological order.
+    pub fn find_by_author(&self, name: &str) -> Vec<(usize, &Snapshot)> {
+        self.snapshots
+            .iter()
+            .enumerate()
+            .filter(|(_, snapshot)| snapshot.author.as_deref() == Some(name))
+            .collect()
+    }
+}
+
+/// Matches path components against glob segments, where a `**` segment spans any number of components.
+fn segments_match(segments: &[&str], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|skip| segments_match(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => components
+            .split_first()
+            .is_some_and(|(component, remaining)| {
+                let segment: Vec<char> = segment.chars().collect();
+                let component: Vec<char> = component.chars().collect();
+                component_matches(&segment, &component) && segments_match(rest, remaining)
+            }),
+    }
+}
+
+/// Matches a single path component against a glob segment, where `*` matches any run of characters
+/// and `?` matches exactly one.
+fn component_matches(segment: &[char], component: &[char]) -> bool {
+    match (segment.first(), component.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            component_matches(&segment[1..], component)
+                || (!component.is_empty() && component_matches(segment, &component[1..]))
+        }
+        (Some('?'), Some(_)) => component_matches(&segment[1..], &component[1..]),
+        (Some(expected), Some(actual)) if expected == actual => {
+            component_matches(&segment[1..], &component[1..])
+        }
+        _ => false,
+    }
+}
+
+/// A path as written to a manifest: its text when it is valid UTF-8, and its raw bytes otherwise.
+/// Raw bytes are only produced on Unix; other platforms fall back to a lossy textual form.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EncodedPath {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl EncodedPath {
+    /// Encodes a path, keeping every byte of names that are not valid UTF-8 on Unix.
+    fn encode(path: &Path) -> Self {
+        match path.to_str() {
+            Some(text) => EncodedPath::Text(text.to_owned()),
+            #[cfg(unix)]
+            None => {
+                use std::os::unix::ffi::OsStrExt;
+                EncodedPath::Bytes(path.as_os_str().as_bytes().to_vec())
+            }
+            #[cfg(not(unix))]
+            None => EncodedPath::Text(path.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// Decodes a path, failing only for raw bytes that the platform cannot represent.
+    fn decode(self) -> Result<PathBuf, String> {
+        match self {
+            EncodedPath::Text(text) => Ok(PathBuf::from(text)),
+            #[cfg(unix)]
+            EncodedPath::Bytes(bytes) => {
+                use std::os::unix::ffi::OsStringExt;
+                Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+            }
+            #[cfg(not(unix))]
+            EncodedPath::Bytes(bytes) => String::from_utf8(bytes)
+                .map(PathBuf::from)
+                .map_err(|e| format!("path is not representable on this platform: {e}")),
+        }
+    }
+}
+
+/// Serializes path-keyed maps as sequences of `[path, value]` entries, since JSON object keys must
+/// be valid UTF-8 strings. Maps written as objects with string keys are still read.
+mod path_map {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    use super::EncodedPath;
+
+    /// Writes every entry with its path encoded losslessly.
+    pub fn serialize<S, V>(map: &HashMap<PathBuf, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        V: Serialize,
+    {
+        serializer.collect_seq(
+            map.iter()
+                .map(|(path, value)| (EncodedPath::encode(path), value)),
+        )
+    }
+
+    /// Reads either a sequence of entries or a legacy object keyed by path strings.
+    pub fn deserialize<'de, D, V>(deserializer: D) -> Result<HashMap<PathBuf, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Deserialize<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Encoded<V> {
+            Entries(Vec<(EncodedPath, V)>),
+            Legacy(HashMap<PathBuf, V>),
+        }
+
+        match Encoded::deserialize(deserializer)? {
+            Encoded::Entries(entries) => entries
+                .into_iter()
+                .map(|(path, value)| Ok((path.decode().map_err(D::Error::custom)?, value)))
+                .collect(),
+            Encoded::Legacy(map) => Ok(map),
+        }
+    }
+}
+
+/// Serializes path sets as sequences of losslessly encoded paths.
+mod path_set {
+    use std::{collections::HashSet, path::PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    use super::EncodedPath;
+
+    /// Writes every path encoded losslessly.
+    pub fn serialize<S>(set: &HashSet<PathBuf>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(set.iter().map(|path| EncodedPath::encode(path)))
+    }
+
+    /// Reads a sequence of paths, each either a string or raw bytes.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashSet<PathBuf>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<EncodedPath>::deserialize(deserializer)?
+            .into_iter()
+            .map(|path| path.decode().map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a history of empty snapshots carrying the given comments.
+    fn history(comments: &[Option<&str>]) -> History {
+        History {
+            snapshots: comments
+                .iter()
+                .map(|comment| Snapshot {
+                    comment: comment.map(String::from),
+                    created_at: None,
+                    author: None,
+                    scope: Scope::default(),
+                    manifest: Manifest {
+                        files: HashMap::new(),
+                        empty_directories: HashSet::new(),
+                        modes: HashMap::new(),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_diff() {
+        let older = Manifest {
+            files: HashMap::from([
+                (PathBuf::from("same.txt"), Id { digest: 1 }),
+                (PathBuf::from("changed.txt"), Id { digest: 2 }),
+                (PathBuf::from("removed.txt"), Id { digest: 3 }),
+            ]),
+            empty_directories: HashSet::from([PathBuf::from("kept"), PathBuf::from("dropped")]),
+            modes: HashMap::new(),
+        };
+        let newer = Manifest {
+            files: HashMap::from([
+                (PathBuf::from("same.txt"), Id { digest: 1 }),
+                (PathBuf::from("changed.txt"), Id { digest: 4 }),
+                (PathBuf::from("added.txt"), Id { digest: 5 }),
+            ]),
+            empty_directories: HashSet::from([PathBuf::from("kept"), PathBuf::from("created")]),
+            modes: HashMap::new(),
+        };
+
+        assert_eq!(
+            older.diff(&newer),
+            ManifestDiff {
+                added: vec![PathBuf::from("added.txt")],
+                removed: vec![PathBuf::from("removed.txt")],
+                modified: vec![PathBuf::from("changed.txt")],
+                added_directories: vec![PathBuf::from("created")],
+                removed_directories: vec![PathBuf::from("dropped")],
+            }
+        );
+        assert_eq!(newer.diff(&newer), ManifestDiff::default());
+    }
+
+    #[test]
+    fn test_find_by_comment() {
+        let hist = history(&[Some("initial draft"), None, Some("fixed the bugfix")]);
+
+        let found = hist.find_by_comment("bugfix");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 2);
+
+        assert!(hist.find_by_comment("Bugfix").is_empty());
+        assert!(hist.find_by_comment("missing").is_empty());
+
+        let indices: Vec<usize> = hist
+            .find_by_comment("")
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(indices, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_find_by_author() {
+        let mut hist = history(&[None, None, None]);
+        hist.snapshots[0].author = Some("alice".to_string());
+        hist.snapshots[2].author = Some("alice".to_string());
+
+        let indices: Vec<usize> = hist
+            .find_by_author("alice")
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(indices, vec![0, 2]);
+
+        assert!(hist.find_by_author("Alice").is_empty());
+        assert!(hist.find_by_author("ali").is_empty());
+        assert!(hist.find_by_author("").is_empty());
+    }
+
+    #[test]
+    fn test_manifest_reads_legacy_path_keys() {
+        let legacy = r#"{
+            "files": {"a.txt": {"digest": 1}},
+            "empty_directories": ["empty"],
+            "modes": {"a.txt": 420}
+        }"#;
+        let manifest: Manifest = serde_json::from_str(legacy).unwrap();
+        assert_eq!(
+            manifest.files,
+            HashMap::from([(PathBuf::from("a.txt"), Id { digest: 1 })])
+        );
+        assert_eq!(
+            manifest.empty_directories,
+            HashSet::from([PathBuf::from("empty")])
+        );
+        assert_eq!(
+            manifest.modes,
+            HashMap::from([(PathBuf::from("a.txt"), 420)])
+        );
+
+        let json = serde_json::to_vec(&manifest).unwrap();
+        assert_eq!(serde_json::from_slice::<Manifest>(&json).unwrap(), manifest);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_manifest_round_trips_non_utf8_paths() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = PathBuf::from(OsStr::from_bytes(b"bad\xffname.txt"));
+        let manifest = Manifest {
+            files: HashMap::from([(path.clone(), Id { digest: 1 })]),
+            empty_directories: HashSet::from([path.join("empty")]),
+            modes: HashMap::from([(path, 0o644)]),
+        };
+
+        let json = serde_json::to_vec(&manifest).unwrap();
+        assert_eq!(serde_json::from_slice::<Manifest>(&json).unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_scope_ignores() {
+        let scope = Scope {
+            max_depth: None,
+            ignore: vec![
+                "target/".to_string(),
+                "*.log".to_string(),
+                "docs/**/draft.md".to_string(),
+                "/build".to_string(),
+            ],
+        };
+
+        assert!(scope.ignores(Path::new("target"), true));
+        assert!(scope.ignores(Path::new("crates/core/target"), true));
+        assert!(!scope.ignores(Path::new("target"), false));
+
+        assert!(scope.ignores(Path::new("debug.log"), false));
+        assert!(scope.ignores(Path::new("logs/nested/debug.log"), false));
+        assert!(!scope.ignores(Path::new("debug.log.txt"), false));
+
+        assert!(scope.ignores(Path::new("docs/draft.md"), false));
+        assert!(scope.ignores(Path::new("docs/guide/intro/draft.md"), false));
+        assert!(!scope.ignores(Path::new("notes/draft.md"), false));
+
+        assert!(scope.ignores(Path::new("build"), true));
+        assert!(!scope.ignores(Path::new("src/build"), true));
+
+        assert!(!Scope::default().ignores(Path::new("target"), true));
+    }
+}
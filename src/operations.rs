@@ -1,16 +1,19 @@
 use std::{
     collections::{HashMap, HashSet},
     fs,
+    io::Write,
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use log::{debug, info, trace};
 use rayon::prelude::*;
+use tempfile::NamedTempFile;
 use thiserror::Error;
 use walkdir::WalkDir;
 
 use crate::{
-    model::{History, Id, Manifest, Snapshot},
+    model::{History, Id, Manifest, ManifestDiff, Scope, Snapshot},
     store::{FileStore, StoreError},
 };
 
@@ -28,6 +31,49 @@ pub enum OperationError {
     /// An error occurred while processing JSON data.
     #[error(transparent)]
     Json(#[from] serde_json::Error),
+
+    /// No history has been recorded for the directory.
+    #[error("no history recorded for {0:?}")]
+    HistoryNotFound(PathBuf),
+
+    /// The requested version index lies beyond the recorded history.
+    #[error("version {0} is out of range")]
+    VersionOutOfRange(usize),
+
+    /// An error occurred while persisting a temporary file.
+    #[error(transparent)]
+    Persist(#[from] tempfile::PersistError),
+
+    /// Another operation currently holds the history of the directory.
+    #[error("history of {0:?} is locked by another operation")]
+    Locked(PathBuf),
+
+    /// An error occurred while processing a specific file, identified by its path.
+    #[error("failed on {}: {source}", path.display())]
+    File {
+        /// The path of the file that could not be processed.
+        path: PathBuf,
+        /// The underlying cause of the failure.
+        #[source]
+        source: Box<OperationError>,
+    },
+
+    /// A data blob referenced by a manifest is absent from the data store.
+    #[error("referenced data blob {} is missing", .0.digest)]
+    MissingBlob(Id),
+
+    /// A stored data blob no longer matches the identity it was saved under.
+    #[error("data blob {} does not match its recorded identity", .0.digest)]
+    Corrupted(Id),
+
+    /// More snapshots were requested to be rolled back than the history records.
+    #[error("cannot roll back {requested} of {recorded} recorded snapshots")]
+    RollbackOutOfRange {
+        /// The number of snapshots requested to be discarded.
+        requested: usize,
+        /// The number of snapshots the history actually records.
+        recorded: usize,
+    },
 }
 
 /// Computes a structural identity (hash) for the physical contents of a file.
@@ -37,38 +83,59 @@ fn data_id(path: &Path) -> Result<Id, OperationError> {
     let mut file = fs::File::open(path)?;
 
     std::io::copy(&mut file, &mut hasher)?;
-    let hash_bytes = hasher.finalize();
+    let id = digest_id(hasher.finalize());
 
-    let mut digest_bytes = [0u8; 8];
-    digest_bytes.copy_from_slice(&hash_bytes.as_bytes()[0..8]);
-    let digest = u64::from_le_bytes(digest_bytes);
+    debug!("Computed data ID {} for file: {:?}", id.digest, path);
+    Ok(id)
+}
 
-    debug!("Computed data ID {} for file: {:?}", digest, path);
-    Ok(Id { digest })
+/// Computes a structural identity (hash) for in-memory contents, matching `data_id` for equal bytes.
+fn bytes_id(data: &[u8]) -> Id {
+    let id = digest_id(blake3::hash(data));
+    trace!("Computed data ID {} for {} bytes", id.digest, data.len());
+    id
 }
 
 /// Computes a structural identity (hash) for a logical file path.
+/// Hashes the raw platform encoding so distinct non-UTF-8 paths never share an identity,
+/// while valid UTF-8 paths keep the identity derived from their textual form.
 fn path_id(path: &Path) -> Id {
     trace!("Computing path ID for: {:?}", path);
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(path.to_string_lossy().as_bytes());
-    let hash_bytes = hasher.finalize();
+    let id = digest_id(blake3::hash(path.as_os_str().as_encoded_bytes()));
+    debug!("Computed path ID {} for: {:?}", id.digest, path);
+    id
+}
 
+/// Derives an identity from the leading 8 bytes of a hash, read as a little-endian integer.
+fn digest_id(hash: blake3::Hash) -> Id {
     let mut digest_bytes = [0u8; 8];
-    digest_bytes.copy_from_slice(&hash_bytes.as_bytes()[0..8]);
+    digest_bytes.copy_from_slice(&hash.as_bytes()[0..8]);
+    Id {
+        digest: u64::from_le_bytes(digest_bytes),
+    }
+}
 
-    let digest = u64::from_le_bytes(digest_bytes);
-    debug!("Computed path ID {} for: {:?}", digest, path);
-    Id { digest }
+/// Reads the permission bits of a file, or `None` on platforms without Unix permissions.
+fn file_mode(path: &Path) -> Result<Option<u32>, OperationError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(Some(fs::metadata(path)?.permissions().mode() & 0o777))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(None)
+    }
 }
 
 /// Stores a file's contents into the data store if its intrinsic identity does not already exist.
-/// Returns the file's normalized relative path and its computed identity.
+/// Returns the file's normalized relative path, its computed identity, and its permission bits.
 fn store_file(
     store: &FileStore,
     path: &Path,
     base_dir: &Path,
-) -> Result<(PathBuf, Id), OperationError> {
+) -> Result<(PathBuf, Id, Option<u32>), OperationError> {
     trace!("Evaluating file for storage: {:?}", path);
     let id = data_id(path)?;
 
@@ -86,34 +153,95 @@ fn store_file(
 
     let rel_path = path.strip_prefix(base_dir).unwrap_or(path).to_path_buf();
 
-    Ok((rel_path, id))
+    Ok((rel_path, id, file_mode(path)?))
 }
 
-/// Generates a manifest encapsulating the current physical state of a directory boundary.
-/// Discovers all files, computes their identities, and ensures their contents are safely stored.
-fn manifest(store: &FileStore, directory: &Path) -> Result<Manifest, OperationError> {
-    info!("Generating manifest for directory: {:?}", directory);
-    let mut manifest = Manifest {
-        files: HashMap::new(),
-    };
+/// Walks every entry within a directory boundary that falls inside the given scope, excluding the
+/// boundary itself. Ignored directories are never descended into, and directories are only yielded
+/// when their own contents lie within the depth limit.
+fn entries(directory: &Path, scope: &Scope) -> impl Iterator<Item = walkdir::DirEntry> {
+    let mut walker = WalkDir::new(directory).min_depth(1);
+    if let Some(depth) = scope.max_depth {
+        walker = walker.max_depth(depth + 1);
+    }
 
-    let entries: Vec<PathBuf> = WalkDir::new(directory)
+    walker
         .into_iter()
+        .filter_entry(move |e| {
+            let rel_path = e.path().strip_prefix(directory).unwrap_or(e.path());
+            e.depth() == 0 || !scope.ignores(rel_path, e.file_type().is_dir())
+        })
         .filter_map(|e| e.ok())
+        .filter(move |e| {
+            !e.file_type().is_dir() || scope.max_depth.is_none_or(|depth| e.depth() <= depth)
+        })
+}
+
+/// Discovers every directory within a directory boundary and scope that has no entries at all,
+/// returned relative to the boundary.
+fn empty_directories(directory: &Path, scope: &Scope) -> HashSet<PathBuf> {
+    entries(directory, scope)
+        .filter(|e| e.file_type().is_dir())
+        .filter(|e| fs::read_dir(e.path()).is_ok_and(|mut entries| entries.next().is_none()))
+        .map(|e| {
+            e.path()
+                .strip_prefix(directory)
+                .unwrap_or(e.path())
+                .to_path_buf()
+        })
+        .collect()
+}
+
+/// Discovers every regular file within a directory boundary and scope, excluding symbolic links.
+fn files(directory: &Path, scope: &Scope) -> Vec<PathBuf> {
+    entries(directory, scope)
         .filter(|e| e.file_type().is_file() && !e.file_type().is_symlink())
         .map(|e| e.into_path())
-        .collect();
+        .collect()
+}
+
+/// Assembles a manifest from the relative path, identity, and permission bits of each file.
+fn assemble(
+    entries: Vec<(PathBuf, Id, Option<u32>)>,
+    empty_directories: HashSet<PathBuf>,
+) -> Manifest {
+    let mut manifest = Manifest {
+        files: HashMap::new(),
+        empty_directories,
+        modes: HashMap::new(),
+    };
+    for (rel_path, id, mode) in entries {
+        if let Some(mode) = mode {
+            manifest.modes.insert(rel_path.clone(), mode);
+        }
+        manifest.files.insert(rel_path, id);
+    }
+    manifest
+}
+
+/// Generates a manifest encapsulating the current physical state of a directory boundary.
+/// Discovers all files, computes their identities, and ensures their contents are safely stored.
+fn manifest(
+    store: &FileStore,
+    directory: &Path,
+    scope: &Scope,
+) -> Result<Manifest, OperationError> {
+    info!("Generating manifest for directory: {:?}", directory);
+    let entries = files(directory, scope);
 
     debug!("Found {} files to process for manifest", entries.len());
 
-    let new_entries: Vec<(PathBuf, Id)> = entries
+    let new_entries: Vec<(PathBuf, Id, Option<u32>)> = entries
         .par_iter()
-        .map(|path| store_file(store, path, directory))
+        .map(|path| {
+            store_file(store, path, directory).map_err(|source| OperationError::File {
+                path: path.clone(),
+                source: Box::new(source),
+            })
+        })
         .collect::<Result<Vec<_>, OperationError>>()?;
 
-    for (rel_path, id) in new_entries {
-        manifest.files.insert(rel_path, id);
-    }
+    let manifest = assemble(new_entries, empty_directories(directory, scope));
 
     info!(
         "Successfully generated manifest with {} entries",
@@ -122,16 +250,59 @@ fn manifest(store: &FileStore, directory: &Path) -> Result<Manifest, OperationEr
     Ok(manifest)
 }
 
+/// Computes the manifest describing a directory's current physical state without storing any data.
+fn scan(directory: &Path, scope: &Scope) -> Result<Manifest, OperationError> {
+    debug!(
+        "Scanning directory state without persisting: {:?}",
+        directory
+    );
+    let entries = files(directory, scope)
+        .par_iter()
+        .map(
+            |path| -> Result<(PathBuf, Id, Option<u32>), OperationError> {
+                let rel_path = path.strip_prefix(directory).unwrap_or(path).to_path_buf();
+                Ok((rel_path, data_id(path)?, file_mode(path)?))
+            },
+        )
+        .collect::<Result<Vec<_>, OperationError>>()?;
+    Ok(assemble(entries, empty_directories(directory, scope)))
+}
+
+/// Determines whether a freshly scanned manifest still matches a recorded one. Permission bits are
+/// only compared where both manifests know them, so manifests recorded before modes were captured,
+/// or on another platform, do not count as modified.
+fn unchanged(recorded: &Manifest, current: &Manifest) -> bool {
+    recorded.files == current.files
+        && recorded.empty_directories == current.empty_directories
+        && recorded.modes.iter().all(|(path, mode)| {
+            current
+                .modes
+                .get(path)
+                .is_none_or(|current| current == mode)
+        })
+}
+
 /// Creates a new immutable snapshot encompassing the directory's current manifest and an optional note.
 fn snapshot(
     store: &FileStore,
     directory: &Path,
     comment: Option<String>,
+    scope: &Scope,
 ) -> Result<Snapshot, OperationError> {
     info!("Creating snapshot for directory: {:?}", directory);
-    let manifest = manifest(store, directory)?;
+    let manifest = manifest(store, directory, scope)?;
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_secs());
     debug!("Snapshot created successfully");
-    Ok(Snapshot { comment, manifest })
+    Ok(Snapshot {
+        comment,
+        created_at,
+        author: None,
+        scope: scope.clone(),
+        manifest,
+    })
 }
 
 /// Retrieves the sequential historical record of snapshots for a given directory context.
@@ -153,21 +324,137 @@ pub fn history(
     }
 }
 
+/// Retrieves the historical record of a directory that operations require to exist, failing with
+/// `HistoryNotFound` when nothing has been recorded for it.
+fn recorded_history(
+    history_store: &FileStore,
+    directory: &Path,
+) -> Result<History, OperationError> {
+    history(history_store, directory)?
+        .ok_or_else(|| OperationError::HistoryNotFound(directory.to_path_buf()))
+}
+
+/// An advisory lock on the history of a single directory, held for the duration of a
+/// read-modify-write of that history. The lock is a `<digest>.lock` file beside the history in the
+/// history store, created exclusively and removed again on drop.
+struct HistoryLock {
+    path: PathBuf,
+}
+
+impl HistoryLock {
+    /// Acquires the lock on the history of `directory`, failing with `Locked` while another holder
+    /// is active.
+    fn acquire(history_store: &FileStore, directory: &Path) -> Result<Self, OperationError> {
+        let path = history_store
+            .directory()
+            .join(format!("{}.lock", path_id(directory).digest));
+        trace!("Acquiring history lock: {:?}", path);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => Ok(HistoryLock { path }),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                debug!("History of {:?} is already locked", directory);
+                Err(OperationError::Locked(directory.to_path_buf()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for HistoryLock {
+    /// Releases the lock by removing its file.
+    fn drop(&mut self) {
+        trace!("Releasing history lock: {:?}", self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
 /// Captures the current state of a directory and appends it to its canonical historical record.
+/// Returns the 0-indexed position of the newly appended snapshot.
 pub fn save(
     data_store: &FileStore,
     history_store: &FileStore,
     directory: &Path,
     comment: Option<String>,
-) -> Result<(), OperationError> {
+) -> Result<usize, OperationError> {
+    save_with_scope(
+        data_store,
+        history_store,
+        directory,
+        comment,
+        &Scope::default(),
+    )
+}
+
+/// Captures the state of the entries of a directory that fall within `scope` and appends it to its
+/// canonical historical record. Returns the 0-indexed position of the newly appended snapshot.
+pub fn save_with_scope(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    directory: &Path,
+    comment: Option<String>,
+    scope: &Scope,
+) -> Result<usize, OperationError> {
     info!("Saving new snapshot for directory: {:?}", directory);
-    let snapshot = snapshot(data_store, directory, comment.clone())?;
+    let snapshot = snapshot(data_store, directory, comment, scope)?;
+    let _lock = HistoryLock::acquire(history_store, directory)?;
     let mut hist = history(history_store, directory)?.unwrap_or_default();
     hist.snapshots.push(snapshot);
+    let index = hist.snapshots.len() - 1;
 
     history_store.set(path_id(directory), &serde_json::to_vec(&hist)?)?;
-    info!("Successfully appended snapshot to history");
-    Ok(())
+    info!("Successfully appended snapshot {} to history", index);
+    Ok(index)
+}
+
+/// Captures the current state of a directory and appends it to its canonical historical record,
+/// unless it is structurally identical to the most recently saved snapshot. The comparison only scans
+/// the directory, so nothing is stored when it is unchanged. The new snapshot keeps the scope of the
+/// most recently saved one. Returns the 0-indexed position of the new snapshot, or `None` when
+/// nothing was recorded.
+pub fn save_if_changed(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    directory: &Path,
+    comment: Option<String>,
+) -> Result<Option<usize>, OperationError> {
+    info!("Saving snapshot if changed for directory: {:?}", directory);
+    if !is_modified(history_store, directory)? {
+        debug!("Directory state unchanged since latest snapshot, skipping save");
+        return Ok(None);
+    }
+
+    let scope = history(history_store, directory)?
+        .and_then(|hist| hist.snapshots.into_iter().last())
+        .map(|latest| latest.scope)
+        .unwrap_or_default();
+    let index = save_with_scope(data_store, history_store, directory, comment, &scope)?;
+    Ok(Some(index))
+}
+
+/// Determines whether a directory's current state differs from its most recently saved snapshot.
+/// Only entries within the scope of that snapshot are compared.
+/// A directory without any history is considered modified as soon as it contains a file or an
+/// empty directory.
+pub fn is_modified(history_store: &FileStore, directory: &Path) -> Result<bool, OperationError> {
+    info!(
+        "Checking directory for unsaved modifications: {:?}",
+        directory
+    );
+    let modified = match history(history_store, directory)?
+        .and_then(|hist| hist.snapshots.into_iter().last())
+    {
+        Some(latest) => !unchanged(&latest.manifest, &scan(directory, &latest.scope)?),
+        None => {
+            let current = scan(directory, &Scope::default())?;
+            !(current.files.is_empty() && current.empty_directories.is_empty())
+        }
+    };
+    debug!("Directory modification status: {}", modified);
+    Ok(modified)
 }
 
 /// Defines the resolution target for extracting a snapshot from history.
@@ -186,7 +473,80 @@ impl Default for Version {
     }
 }
 
+/// Resolves a version target to the index of the snapshot it designates within a history.
+/// Rejects indices beyond the recorded history, including `Latest` on an empty history.
+fn resolve(hist: &History, version: Version) -> Result<usize, OperationError> {
+    let target_index = match version {
+        Version::Latest => hist.snapshots.len().saturating_sub(1),
+        Version::Specific(idx) => idx,
+    };
+
+    debug!("Targeting snapshot timeline index: {}", target_index);
+    if target_index >= hist.snapshots.len() {
+        return Err(OperationError::VersionOutOfRange(target_index));
+    }
+    Ok(target_index)
+}
+
+/// Retrieves a data blob, ensuring it is present and still matches its recorded identity.
+/// Stored data that can no longer be decoded is reported as corrupted, just like a content mismatch.
+fn blob(data_store: &FileStore, id: Id) -> Result<Vec<u8>, OperationError> {
+    match data_store.get(id) {
+        Ok(Some(data)) if bytes_id(&data) != id => Err(OperationError::Corrupted(id)),
+        Ok(Some(data)) => Ok(data),
+        Ok(None) => Err(OperationError::MissingBlob(id)),
+        Err(StoreError::Corrupted(_)) => Err(OperationError::Corrupted(id)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically replaces the file at `dest_path` with the contents produced by `write`.
+/// The contents go to a temporary sibling that is renamed into place only once `write` succeeds, so a
+/// failure at any point leaves an existing file untouched. The replacement receives the given Unix
+/// permission bits when known; otherwise it keeps the permissions of the file it replaces, and a new
+/// file receives the default permissions left by the process umask.
+fn replace(
+    dest_path: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut NamedTempFile) -> std::io::Result<()>,
+) -> Result<(), OperationError> {
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+
+    let mut temp_file = builder.tempfile_in(dest_path.parent().unwrap_or(Path::new(".")))?;
+    write(&mut temp_file)?;
+    match mode {
+        #[cfg(unix)]
+        Some(mode) => {
+            use std::os::unix::fs::PermissionsExt;
+            trace!("Applying recorded mode {:o} to {:?}", mode, dest_path);
+            temp_file
+                .as_file()
+                .set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+        _ => {
+            if let Ok(metadata) = fs::metadata(dest_path) {
+                trace!("Carrying over existing permissions of {:?}", dest_path);
+                temp_file
+                    .as_file()
+                    .set_permissions(metadata.permissions())?;
+            }
+        }
+    }
+    temp_file.persist(dest_path)?;
+    Ok(())
+}
+
 /// Reconstructs the physical file structures described by a pure manifest into the target directory.
+/// Each file is written to a temporary sibling and atomically renamed into place, so an interrupted
+/// load never leaves a half-written file behind. Files regain their recorded permission bits, falling
+/// back to those of the file they replace. Blobs whose contents no longer hash to their recorded
+/// identity are rejected rather than written.
 fn load(
     data_store: &FileStore,
     manifest: &Manifest,
@@ -203,23 +563,21 @@ fn load(
             fs::create_dir_all(parent)?;
         }
 
-        match data_store.get(*id)? {
-            Some(data) => fs::write(dest_path, data)?,
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "Referenced data blob missing",
-                )
-                .into());
-            }
-        }
+        let data = blob(data_store, *id)?;
+        let mode = manifest.modes.get(rel_path).copied();
+        replace(&dest_path, mode, |file| file.write_all(&data))?;
+    }
+    for rel_path in &manifest.empty_directories {
+        trace!("Restoring empty directory: {:?}", rel_path);
+        fs::create_dir_all(target_directory.join(rel_path))?;
     }
     debug!("Successfully reified manifest state to filesystem");
     Ok(())
 }
 
 /// Forks a specific historical state into a target directory context, isolating it as a new boundary.
-/// Purges files in the target directory that do not belong to the target state topology.
+/// Purges files and directories in the target directory that do not belong to the target state
+/// topology, leaving those beyond the scope the state was captured with untouched.
 pub fn split(
     data_store: &FileStore,
     history_store: &FileStore,
@@ -231,30 +589,19 @@ pub fn split(
         "Splitting history state from {:?} to {:?}",
         source_directory, target_directory
     );
-    let mut hist = history(history_store, source_directory)?.ok_or_else(|| {
-        std::io::Error::new(std::io::ErrorKind::NotFound, "Source history not found")
-    })?;
+    let _lock = HistoryLock::acquire(history_store, target_directory)?;
+    let mut hist = recorded_history(history_store, source_directory)?;
 
-    let target_index = match version {
-        Version::Latest => hist.snapshots.len().saturating_sub(1),
-        Version::Specific(idx) => idx,
-    };
-
-    debug!("Targeting snapshot timeline index: {}", target_index);
+    let target_index = resolve(&hist, version)?;
     hist.snapshots.truncate(target_index + 1);
 
     history_store.set(path_id(target_directory), &serde_json::to_vec(&hist)?)?;
 
-    let target_manifest = &hist.snapshots[target_index].manifest;
+    let target = &hist.snapshots[target_index];
+    let target_manifest = &target.manifest;
     if target_directory.exists() {
         debug!("Pruning extraneous files from target directory to maintain fidelity");
-        let entries = walkdir::WalkDir::new(target_directory)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file() && !e.file_type().is_symlink())
-            .map(|e| e.into_path());
-
-        for path in entries {
+        for path in files(target_directory, &target.scope) {
             let rel_path = path
                 .strip_prefix(target_directory)
                 .unwrap_or(&path)
@@ -264,6 +611,32 @@ pub fn split(
                 let _ = std::fs::remove_file(path);
             }
         }
+
+        let mut retained: HashSet<&Path> = target_manifest
+            .empty_directories
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+        for rel_path in target_manifest
+            .files
+            .keys()
+            .chain(&target_manifest.empty_directories)
+        {
+            retained.extend(rel_path.ancestors().skip(1));
+        }
+
+        let mut directories: Vec<PathBuf> = entries(target_directory, &target.scope)
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.into_path())
+            .collect();
+        directories.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+        for path in directories {
+            let rel_path = path.strip_prefix(target_directory).unwrap_or(&path);
+            if !retained.contains(rel_path) {
+                trace!("Deleting directory outside of manifest scope: {:?}", path);
+                let _ = std::fs::remove_dir(&path);
+            }
+        }
     }
 
     load(data_store, target_manifest, target_directory)?;
@@ -271,19 +644,51 @@ pub fn split(
     Ok(())
 }
 
-/// Destroys the historical record for a given directory and executes a global garbage collection
-/// routine to reclaim storage from orphaned data blobs lacking any live references.
-pub fn clean(
-    data_store: &FileStore,
+/// Discards a single snapshot from the historical record of a directory, shifting every later
+/// snapshot down by one position. Run `gc` afterwards to reclaim the data blobs only it referenced.
+pub fn remove(
     history_store: &FileStore,
     directory: &Path,
+    index: usize,
+) -> Result<(), OperationError> {
+    info!("Removing snapshot {} for directory: {:?}", index, directory);
+    let _lock = HistoryLock::acquire(history_store, directory)?;
+    let mut hist = recorded_history(history_store, directory)?;
+    let index = resolve(&hist, Version::Specific(index))?;
+
+    hist.snapshots.remove(index);
+    history_store.set(path_id(directory), &serde_json::to_vec(&hist)?)?;
+    info!("Successfully removed snapshot from history");
+    Ok(())
+}
+
+/// Records `author` as the author of the snapshot at `index` in the historical record of a directory,
+/// or clears it when `None`.
+pub fn set_author(
+    history_store: &FileStore,
+    directory: &Path,
+    index: usize,
+    author: Option<String>,
 ) -> Result<(), OperationError> {
     info!(
-        "Commencing aggressive cleanup for directory history: {:?}",
-        directory
+        "Setting author of snapshot {} for directory: {:?}",
+        index, directory
     );
-    history_store.remove(path_id(directory))?;
+    let _lock = HistoryLock::acquire(history_store, directory)?;
+    let mut hist = recorded_history(history_store, directory)?;
+    let index = resolve(&hist, Version::Specific(index))?;
 
+    hist.snapshots[index].author = author;
+    history_store.set(path_id(directory), &serde_json::to_vec(&hist)?)?;
+    debug!("Successfully updated snapshot author");
+    Ok(())
+}
+
+/// Executes a global garbage collection routine that reclaims storage from orphaned data blobs
+/// lacking any live references. Blobs referenced by any recorded history are never removed.
+/// Returns the number of blobs evicted.
+pub fn gc(data_store: &FileStore, history_store: &FileStore) -> Result<usize, OperationError> {
+    info!("Commencing garbage collection of orphaned data blobs");
     let mut used_ids = HashSet::new();
     debug!("Scanning global history state to rebuild reference graph");
     for key in history_store.keys()? {
@@ -302,13 +707,1163 @@ pub fn clean(
         used_ids.len()
     );
 
+    let mut removed = 0;
     for key in data_store.keys()? {
         if !used_ids.contains(&key) {
             trace!("Evicting permanently orphaned data blob: {}", key.digest);
             data_store.remove(key)?;
+            removed += 1;
+        }
+    }
+
+    info!("Garbage collection evicted {} data blobs", removed);
+    Ok(removed)
+}
+
+/// Reconstructs a specific historical state into a target directory without recording any history
+/// for it or touching the source directory. Existing files in the target directory that are not part
+/// of the exported state are left in place.
+pub fn export(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    source_directory: &Path,
+    target_directory: &Path,
+    version: Version,
+) -> Result<(), OperationError> {
+    info!(
+        "Exporting history state from {:?} to {:?}",
+        source_directory, target_directory
+    );
+    let hist = recorded_history(history_store, source_directory)?;
+
+    let snapshot = &hist.snapshots[resolve(&hist, version)?];
+    load(data_store, &snapshot.manifest, target_directory)?;
+    info!("Successfully exported history state to target directory");
+    Ok(())
+}
+
+/// Reads the file contents of a historical state into memory without touching any directory.
+/// Returns each relative path alongside its contents, ordered by path.
+pub fn peek(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    directory: &Path,
+    version: Version,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, OperationError> {
+    info!("Peeking at history state of {:?}", directory);
+    let hist = recorded_history(history_store, directory)?;
+    let snapshot = &hist.snapshots[resolve(&hist, version)?];
+
+    let mut files = snapshot
+        .manifest
+        .files
+        .iter()
+        .map(
+            |(rel_path, id)| -> Result<(PathBuf, Vec<u8>), OperationError> {
+                Ok((rel_path.clone(), blob(data_store, *id)?))
+            },
+        )
+        .collect::<Result<Vec<_>, OperationError>>()?;
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    debug!("Read {} files from history state", files.len());
+    Ok(files)
+}
+
+/// Computes the changes between two historical states of a directory without touching any data.
+/// Rejects either version when it lies beyond the recorded history.
+pub fn diff(
+    history_store: &FileStore,
+    directory: &Path,
+    from: Version,
+    to: Version,
+) -> Result<ManifestDiff, OperationError> {
+    info!("Comparing history states of {:?}", directory);
+    let hist = recorded_history(history_store, directory)?;
+    let older = &hist.snapshots[resolve(&hist, from)?].manifest;
+    let newer = &hist.snapshots[resolve(&hist, to)?].manifest;
+
+    let diff = older.diff(newer);
+    debug!(
+        "Found {} added, {} removed, and {} modified files",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.modified.len()
+    );
+    Ok(diff)
+}
+
+/// Checks that every data blob referenced by a historical state is still present and intact.
+/// Returns `false` when any blob is missing or no longer matches its recorded identity.
+pub fn verify(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    directory: &Path,
+    version: Version,
+) -> Result<bool, OperationError> {
+    info!("Verifying history state integrity for {:?}", directory);
+    let hist = recorded_history(history_store, directory)?;
+    let snapshot = &hist.snapshots[resolve(&hist, version)?];
+
+    for id in snapshot.manifest.files.values() {
+        match blob(data_store, *id) {
+            Ok(_) => {}
+            Err(OperationError::MissingBlob(_) | OperationError::Corrupted(_)) => {
+                debug!("Data blob {} failed verification", id.digest);
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
         }
     }
 
+    info!("All referenced data blobs verified successfully");
+    Ok(true)
+}
+
+/// Trims the historical record of a directory down to its `max` most recent snapshots.
+/// Returns the number of snapshots dropped; run `gc` afterwards to reclaim their data blobs.
+pub fn retain(
+    history_store: &FileStore,
+    directory: &Path,
+    max: usize,
+) -> Result<usize, OperationError> {
+    info!(
+        "Retaining at most {} snapshots for directory: {:?}",
+        max, directory
+    );
+    let _lock = HistoryLock::acquire(history_store, directory)?;
+    let Some(mut hist) = history(history_store, directory)? else {
+        debug!("No history found, nothing to trim");
+        return Ok(0);
+    };
+
+    let excess = hist.snapshots.len().saturating_sub(max);
+    if excess > 0 {
+        hist.snapshots.drain(..excess);
+        history_store.set(path_id(directory), &serde_json::to_vec(&hist)?)?;
+    }
+
+    info!("Dropped {} snapshots from history", excess);
+    Ok(excess)
+}
+
+/// Discards the `n` most recent snapshots from the historical record of a directory.
+/// Validates `n` against the recorded history before changing anything; run `gc` afterwards
+/// to reclaim the data blobs of the discarded snapshots.
+pub fn rollback(
+    history_store: &FileStore,
+    directory: &Path,
+    n: usize,
+) -> Result<(), OperationError> {
+    info!(
+        "Rolling back {} snapshots for directory: {:?}",
+        n, directory
+    );
+    let _lock = HistoryLock::acquire(history_store, directory)?;
+    let mut hist = history(history_store, directory)?.unwrap_or_default();
+    if n > hist.snapshots.len() {
+        return Err(OperationError::RollbackOutOfRange {
+            requested: n,
+            recorded: hist.snapshots.len(),
+        });
+    }
+    if n == 0 {
+        debug!("Nothing to roll back");
+        return Ok(());
+    }
+
+    hist.snapshots.truncate(hist.snapshots.len() - n);
+    history_store.set(path_id(directory), &serde_json::to_vec(&hist)?)?;
+    info!("Successfully rolled back history");
+    Ok(())
+}
+
+/// Transfers the historical record of a directory to a new location after it has been moved or
+/// renamed on disk. Stored data blobs are left untouched since they are independent of location.
+/// Refuses to overwrite a history already recorded for the new location.
+pub fn relocate(
+    history_store: &FileStore,
+    source_directory: &Path,
+    target_directory: &Path,
+) -> Result<(), OperationError> {
+    info!(
+        "Relocating history from {:?} to {:?}",
+        source_directory, target_directory
+    );
+    if !target_directory.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Target directory not found",
+        )
+        .into());
+    }
+
+    let _source_lock = HistoryLock::acquire(history_store, source_directory)?;
+    let _target_lock = HistoryLock::acquire(history_store, target_directory)?;
+    if history(history_store, target_directory)?.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "Target history already exists",
+        )
+        .into());
+    }
+
+    let hist = recorded_history(history_store, source_directory)?;
+
+    history_store.set(path_id(target_directory), &serde_json::to_vec(&hist)?)?;
+    history_store.remove(path_id(source_directory))?;
+    info!("Successfully relocated history");
+    Ok(())
+}
+
+/// Destroys the historical record for a given directory and executes a global garbage collection
+/// routine to reclaim storage from orphaned data blobs lacking any live references.
+pub fn clean(
+    data_store: &FileStore,
+    history_store: &FileStore,
+    directory: &Path,
+) -> Result<(), OperationError> {
+    info!(
+        "Commencing aggressive cleanup for directory history: {:?}",
+        directory
+    );
+    history_store.remove(path_id(directory))?;
+    gc(data_store, history_store)?;
+
     info!("Cleanup sequence successfully finalized");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Creates a data store, a history store, and an empty workspace directory inside `root`.
+    fn setup(root: &Path) -> (FileStore, FileStore, PathBuf) {
+        let data_store = FileStore::new(&root.join("data")).unwrap();
+        let history_store = FileStore::new(&root.join("history")).unwrap();
+        let workspace = root.join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        (data_store, history_store, workspace)
+    }
+
+    #[test]
+    fn test_save_returns_index() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+
+        for expected in 0..3 {
+            fs::write(workspace.join("test.txt"), expected.to_string()).unwrap();
+            let index = save(&data_store, &history_store, &workspace, None).unwrap();
+            assert_eq!(index, expected);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_created_at() {
+        let dir = tempdir().unwrap();
+        let (data_store, _, workspace) = setup(dir.path());
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let snapshot = snapshot(&data_store, &workspace, None, &Scope::default()).unwrap();
+
+        let created_at = snapshot.created_at.unwrap();
+        assert!(created_at >= before && created_at <= before + 1);
+    }
+
+    #[test]
+    fn test_is_modified() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        fs::write(workspace.join("test.txt"), "world").unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_modified_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let script = workspace.join("script.sh");
+        fs::write(&script, "echo hello").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+
+        let mut hist = history(&history_store, &workspace).unwrap().unwrap();
+        hist.snapshots[0].manifest.modes.clear();
+        history_store
+            .set(path_id(&workspace), &serde_json::to_vec(&hist).unwrap())
+            .unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+    }
+
+    #[test]
+    fn test_save_with_scope_ignore() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        for path in [
+            "src/main.rs",
+            "src/debug.log",
+            "target/debug/app",
+            "crates/core/target/out.bin",
+            "crates/core/lib.rs",
+            "docs/guide/draft.md",
+            "docs/guide/intro.md",
+        ] {
+            let path = workspace.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "content").unwrap();
+        }
+        fs::create_dir_all(workspace.join("target").join("empty")).unwrap();
+
+        let scope = Scope {
+            ignore: vec![
+                "target/".to_string(),
+                "*.log".to_string(),
+                "docs/**/draft.md".to_string(),
+            ],
+            ..Scope::default()
+        };
+        save_with_scope(&data_store, &history_store, &workspace, None, &scope).unwrap();
+
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        let snapshot = &hist.snapshots[0];
+        assert_eq!(snapshot.scope, scope);
+        let mut files: Vec<&PathBuf> = snapshot.manifest.files.keys().collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                &PathBuf::from("crates/core/lib.rs"),
+                &PathBuf::from("docs/guide/intro.md"),
+                &PathBuf::from("src/main.rs"),
+            ]
+        );
+        assert!(snapshot.manifest.empty_directories.is_empty());
+
+        fs::write(workspace.join("src").join("trace.log"), "noise").unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        fs::write(workspace.join("src").join("stray.rs"), "stray").unwrap();
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &workspace,
+            Version::Latest,
+        )
+        .unwrap();
+        assert!(!workspace.join("src").join("stray.rs").exists());
+        assert!(workspace.join("target").join("debug").join("app").exists());
+        assert!(workspace.join("src").join("debug.log").exists());
+        assert!(workspace.join("target").join("empty").is_dir());
+    }
+
+    #[test]
+    fn test_save_with_scope_max_depth() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let nested = workspace.join("one").join("two");
+        fs::create_dir_all(nested.join("empty")).unwrap();
+        fs::write(workspace.join("a.txt"), "a").unwrap();
+        fs::write(workspace.join("one").join("b.txt"), "b").unwrap();
+        fs::write(nested.join("c.txt"), "c").unwrap();
+
+        let cases = [
+            (Some(0), vec!["a.txt"], vec![]),
+            (Some(1), vec!["a.txt", "one/b.txt"], vec![]),
+            (Some(2), vec!["a.txt", "one/b.txt", "one/two/c.txt"], vec![]),
+            (
+                Some(3),
+                vec!["a.txt", "one/b.txt", "one/two/c.txt"],
+                vec!["one/two/empty"],
+            ),
+            (
+                None,
+                vec!["a.txt", "one/b.txt", "one/two/c.txt"],
+                vec!["one/two/empty"],
+            ),
+        ];
+        for (max_depth, expected_files, expected_directories) in cases {
+            let scope = Scope {
+                max_depth,
+                ..Scope::default()
+            };
+            let index =
+                save_with_scope(&data_store, &history_store, &workspace, None, &scope).unwrap();
+            let hist = history(&history_store, &workspace).unwrap().unwrap();
+            let snapshot = &hist.snapshots[index];
+            assert_eq!(snapshot.scope, scope);
+
+            let mut files: Vec<&PathBuf> = snapshot.manifest.files.keys().collect();
+            files.sort();
+            let expected_files: Vec<PathBuf> = expected_files.iter().map(PathBuf::from).collect();
+            assert_eq!(files, expected_files.iter().collect::<Vec<_>>());
+
+            let expected_directories: HashSet<PathBuf> =
+                expected_directories.iter().map(PathBuf::from).collect();
+            assert_eq!(snapshot.manifest.empty_directories, expected_directories);
+        }
+    }
+
+    #[test]
+    fn test_split_keeps_files_beyond_scope() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::create_dir_all(workspace.join("vendor")).unwrap();
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        fs::write(workspace.join("vendor").join("dep.txt"), "dep").unwrap();
+        let scope = Scope {
+            max_depth: Some(0),
+            ..Scope::default()
+        };
+        save_with_scope(&data_store, &history_store, &workspace, None, &scope).unwrap();
+
+        fs::write(workspace.join("vendor").join("dep.txt"), "updated").unwrap();
+        fs::write(workspace.join("stray.txt"), "stray").unwrap();
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &workspace,
+            Version::Latest,
+        )
+        .unwrap();
+
+        assert!(!workspace.join("stray.txt").exists());
+        assert_eq!(
+            fs::read_to_string(workspace.join("vendor").join("dep.txt")).unwrap(),
+            "updated"
+        );
+    }
+
+    #[test]
+    fn test_split_prunes_stray_directories() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("a.txt"), "a").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::create_dir_all(workspace.join("sub").join("nested")).unwrap();
+        fs::write(workspace.join("sub").join("nested").join("x.txt"), "x").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &workspace,
+            Version::Specific(0),
+        )
+        .unwrap();
+
+        assert!(!workspace.join("sub").exists());
+        assert!(workspace.join("a.txt").is_file());
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_split_non_utf8_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let name = OsStr::from_bytes(b"bad\xffname");
+        fs::create_dir_all(workspace.join(name).join("empty")).unwrap();
+        fs::write(workspace.join(name).join(name), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        let target = dir.path().join("target");
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join(name).join(name)).unwrap(),
+            "hello"
+        );
+        assert!(target.join(name).join("empty").is_dir());
+        assert!(!is_modified(&history_store, &target).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_split_restores_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let script = workspace.join("script.sh");
+        fs::write(&script, "echo hello").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::write(&script, "echo world").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &workspace,
+            Version::Specific(0),
+        )
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(&script).unwrap(), "echo hello");
+        assert_eq!(mode(&script), 0o755);
+    }
+
+    #[test]
+    fn test_is_modified_empty_directory() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::create_dir_all(workspace.join("assets")).unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        fs::remove_dir(workspace.join("assets")).unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+    }
+
+    #[test]
+    fn test_save_error_names_file() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+
+        fs::remove_dir_all(data_store.directory()).unwrap();
+        fs::write(data_store.directory(), "not a directory").unwrap();
+
+        let err = save(&data_store, &history_store, &workspace, None).unwrap_err();
+        match &err {
+            OperationError::File { path, .. } => assert_eq!(path, &workspace.join("test.txt")),
+            other => panic!("unexpected error: {other:?}"),
+        }
+        assert!(err.to_string().starts_with(&format!(
+            "failed on {}: ",
+            workspace.join("test.txt").display()
+        )));
+    }
+
+    #[test]
+    fn test_save_if_changed() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+
+        let save_changed =
+            || save_if_changed(&data_store, &history_store, &workspace, None).unwrap();
+        assert_eq!(save_changed(), Some(0));
+
+        let id = data_id(&workspace.join("test.txt")).unwrap();
+        data_store.remove(id).unwrap();
+        assert_eq!(save_changed(), None);
+        assert!(data_store.get(id).unwrap().is_none());
+
+        fs::write(workspace.join("test.txt"), "world").unwrap();
+        assert_eq!(save_changed(), Some(1));
+
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), 2);
+    }
+
+    #[test]
+    fn test_save_while_history_locked() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+
+        let lock = HistoryLock::acquire(&history_store, &workspace).unwrap();
+        assert!(matches!(
+            HistoryLock::acquire(&history_store, &workspace),
+            Err(OperationError::Locked(_))
+        ));
+        assert!(matches!(
+            save(&data_store, &history_store, &workspace, None),
+            Err(OperationError::Locked(_))
+        ));
+        assert!(history(&history_store, &workspace).unwrap().is_none());
+
+        drop(lock);
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), 1);
+        assert_eq!(fs::read_dir(history_store.directory()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_remove() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        for i in 0..3 {
+            fs::write(workspace.join("test.txt"), i.to_string()).unwrap();
+            save(&data_store, &history_store, &workspace, Some(i.to_string())).unwrap();
+        }
+
+        remove(&history_store, &workspace, 1).unwrap();
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        let comments: Vec<Option<&str>> = hist
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.comment.as_deref())
+            .collect();
+        assert_eq!(comments, vec![Some("0"), Some("2")]);
+
+        let target = dir.path().join("target");
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Specific(1),
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(target.join("test.txt")).unwrap(), "2");
+
+        assert!(matches!(
+            remove(&history_store, &workspace, 2),
+            Err(OperationError::VersionOutOfRange(2))
+        ));
+        assert_eq!(gc(&data_store, &history_store).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_set_author() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        for i in 0..3 {
+            fs::write(workspace.join("test.txt"), i.to_string()).unwrap();
+            save(&data_store, &history_store, &workspace, None).unwrap();
+        }
+
+        for index in [0, 2] {
+            set_author(&history_store, &workspace, index, Some("alice".to_string())).unwrap();
+        }
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        let indices: Vec<usize> = hist
+            .find_by_author("alice")
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(indices, vec![0, 2]);
+
+        set_author(&history_store, &workspace, 2, None).unwrap();
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.find_by_author("alice").len(), 1);
+
+        assert!(matches!(
+            set_author(&history_store, &workspace, 3, None),
+            Err(OperationError::VersionOutOfRange(3))
+        ));
+    }
+
+    #[test]
+    fn test_split_replaces_files_atomically() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let target = dir.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("test.txt"), "stale").unwrap();
+
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("test.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_replace_keeps_original_on_failure() {
+        let dir = tempdir().unwrap();
+        let dest_path = dir.path().join("test.txt");
+        fs::write(&dest_path, "original").unwrap();
+
+        let result = replace(&dest_path, None, |file| {
+            file.write_all(b"partial")?;
+            Err(std::io::Error::other("interrupted"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&dest_path).unwrap(), "original");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "stale").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        replace(&script, None, |file| file.write_all(b"echo hello")).unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(), "echo hello");
+        assert_eq!(mode(&script), 0o755);
+
+        let created = dir.path().join("new.txt");
+        replace(&created, None, |file| file.write_all(b"new")).unwrap();
+        let reference = dir.path().join("reference.txt");
+        fs::write(&reference, "reference").unwrap();
+        assert_eq!(mode(&created), mode(&reference));
+
+        replace(&created, Some(0o700), |file| file.write_all(b"new")).unwrap();
+        assert_eq!(mode(&created), 0o700);
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::create_dir_all(workspace.join("nested")).unwrap();
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        fs::write(workspace.join("nested").join("inner.txt"), "inner").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::write(workspace.join("test.txt"), "world").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let target = dir.path().join("export");
+        export(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Specific(0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("test.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("nested").join("inner.txt")).unwrap(),
+            "inner"
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.join("test.txt")).unwrap(),
+            "world"
+        );
+        assert!(history(&history_store, &target).unwrap().is_none());
+
+        let missing = export(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Specific(5),
+        );
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_peek() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("a.txt"), "first").unwrap();
+        fs::write(workspace.join("b.txt"), "second").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::write(workspace.join("a.txt"), "changed").unwrap();
+        let files = peek(
+            &data_store,
+            &history_store,
+            &workspace,
+            Version::Specific(0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                (PathBuf::from("a.txt"), b"first".to_vec()),
+                (PathBuf::from("b.txt"), b"second".to_vec()),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.join("a.txt")).unwrap(),
+            "changed"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("same.txt"), "same").unwrap();
+        fs::write(workspace.join("changed.txt"), "before").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        fs::write(workspace.join("changed.txt"), "after").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let changes = diff(
+            &history_store,
+            &workspace,
+            Version::Specific(0),
+            Version::Latest,
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            ManifestDiff {
+                modified: vec![PathBuf::from("changed.txt")],
+                ..ManifestDiff::default()
+            }
+        );
+
+        let result = diff(
+            &history_store,
+            &workspace,
+            Version::Specific(0),
+            Version::Specific(2),
+        );
+        assert!(matches!(result, Err(OperationError::VersionOutOfRange(2))));
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        assert!(verify(&data_store, &history_store, &workspace, Version::Latest).unwrap());
+
+        let id = data_id(&workspace.join("test.txt")).unwrap();
+        data_store.set(id, b"tampered").unwrap();
+        assert!(!verify(&data_store, &history_store, &workspace, Version::Latest).unwrap());
+
+        let target = dir.path().join("export");
+        let result = export(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        );
+        assert!(matches!(result, Err(OperationError::Corrupted(key)) if key == id));
+        assert!(!target.join("test.txt").exists());
+    }
+
+    #[test]
+    fn test_export_corrupted_frame() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello".repeat(100)).unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let id = data_id(&workspace.join("test.txt")).unwrap();
+        let blob_path = data_store.directory().join(format!("{}.evdata", id.digest));
+        let mut stored = fs::read(&blob_path).unwrap();
+        let last = stored.len() - 1;
+        stored[last] ^= 0xFF;
+        fs::write(&blob_path, stored).unwrap();
+
+        assert!(!verify(&data_store, &history_store, &workspace, Version::Latest).unwrap());
+
+        let target = dir.path().join("export");
+        let result = export(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        );
+        assert!(matches!(result, Err(OperationError::Corrupted(key)) if key == id));
+        assert!(!target.join("test.txt").exists());
+    }
+
+    #[test]
+    fn test_split_restores_empty_directories() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::create_dir_all(workspace.join("assets").join("empty")).unwrap();
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+        assert!(!is_modified(&history_store, &workspace).unwrap());
+
+        fs::remove_dir_all(workspace.join("assets")).unwrap();
+        assert!(is_modified(&history_store, &workspace).unwrap());
+
+        let target = dir.path().join("target");
+        split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        )
+        .unwrap();
+
+        assert!(target.join("assets").join("empty").is_dir());
+        assert!(target.join("test.txt").is_file());
+    }
+
+    #[test]
+    fn test_missing_history() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let target = dir.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        let missing = |result: Result<(), OperationError>| matches!(result, Err(OperationError::HistoryNotFound(path)) if path == workspace);
+
+        assert!(missing(split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        )));
+        assert!(missing(export(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Latest,
+        )));
+        assert!(missing(
+            peek(&data_store, &history_store, &workspace, Version::Latest).map(|_| ())
+        ));
+        assert!(missing(
+            diff(&history_store, &workspace, Version::Latest, Version::Latest).map(|_| ())
+        ));
+        assert!(missing(
+            verify(&data_store, &history_store, &workspace, Version::Latest).map(|_| ())
+        ));
+        assert!(missing(remove(&history_store, &workspace, 0)));
+        assert!(missing(set_author(&history_store, &workspace, 0, None)));
+        assert!(missing(relocate(&history_store, &workspace, &target)));
+        assert!(history(&history_store, &target).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_out_of_range() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let target = dir.path().join("target");
+        let result = split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &target,
+            Version::Specific(5),
+        );
+
+        assert!(matches!(result, Err(OperationError::VersionOutOfRange(5))));
+        assert!(history(&history_store, &target).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_missing_blob() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let id = data_id(&workspace.join("test.txt")).unwrap();
+        data_store.remove(id).unwrap();
+
+        let result = split(
+            &data_store,
+            &history_store,
+            &workspace,
+            &dir.path().join("target"),
+            Version::Latest,
+        );
+        assert!(matches!(result, Err(OperationError::MissingBlob(key)) if key == id));
+    }
+
+    #[test]
+    fn test_retain() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        let max = 3;
+        for i in 0..max + 2 {
+            fs::write(workspace.join("test.txt"), i.to_string()).unwrap();
+            save(&data_store, &history_store, &workspace, None).unwrap();
+        }
+
+        assert_eq!(retain(&history_store, &workspace, max).unwrap(), 2);
+        assert_eq!(gc(&data_store, &history_store).unwrap(), 2);
+
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), max);
+        for i in 0..max {
+            let target = dir.path().join(format!("target_{}", i));
+            export(
+                &data_store,
+                &history_store,
+                &workspace,
+                &target,
+                Version::Specific(i),
+            )
+            .unwrap();
+            assert_eq!(
+                fs::read_to_string(target.join("test.txt")).unwrap(),
+                (i + 2).to_string()
+            );
+        }
+
+        assert_eq!(retain(&history_store, &workspace, max).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        for i in 0..3 {
+            fs::write(workspace.join("test.txt"), i.to_string()).unwrap();
+            save(&data_store, &history_store, &workspace, None).unwrap();
+        }
+
+        assert!(matches!(
+            rollback(&history_store, &workspace, 4),
+            Err(OperationError::RollbackOutOfRange {
+                requested: 4,
+                recorded: 3
+            })
+        ));
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), 3);
+
+        rollback(&history_store, &workspace, 2).unwrap();
+        let hist = history(&history_store, &workspace).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), 1);
+
+        let files = peek(&data_store, &history_store, &workspace, Version::Latest).unwrap();
+        assert_eq!(files, vec![(PathBuf::from("test.txt"), b"0".to_vec())]);
+    }
+
+    #[test]
+    fn test_relocate() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let moved = dir.path().join("moved");
+        fs::rename(&workspace, &moved).unwrap();
+        relocate(&history_store, &workspace, &moved).unwrap();
+
+        assert!(history(&history_store, &workspace).unwrap().is_none());
+        let hist = history(&history_store, &moved).unwrap().unwrap();
+        assert_eq!(hist.snapshots.len(), 1);
+
+        let target = dir.path().join("target");
+        export(
+            &data_store,
+            &history_store,
+            &moved,
+            &target,
+            Version::Latest,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("test.txt")).unwrap(),
+            "hello"
+        );
+
+        assert!(relocate(&history_store, &moved, &dir.path().join("absent")).is_err());
+    }
+
+    #[test]
+    fn test_path_id_utf8_compatible() {
+        let path = Path::new("/projects/música");
+        let hash_bytes = blake3::hash(path.to_string_lossy().as_bytes());
+
+        let mut digest_bytes = [0u8; 8];
+        digest_bytes.copy_from_slice(&hash_bytes.as_bytes()[0..8]);
+        let digest = u64::from_le_bytes(digest_bytes);
+
+        assert_eq!(path_id(path), Id { digest });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_id_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let first = Path::new(OsStr::from_bytes(b"/projects/\xff"));
+        let second = Path::new(OsStr::from_bytes(b"/projects/\xfe"));
+        assert_eq!(first.to_string_lossy(), second.to_string_lossy());
+        assert_ne!(path_id(first), path_id(second));
+    }
+
+    #[test]
+    fn test_gc() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        let stray = Id { digest: 12345 };
+        data_store.set(stray, b"ghost payload").unwrap();
+
+        assert_eq!(gc(&data_store, &history_store).unwrap(), 1);
+        assert!(data_store.get(stray).unwrap().is_none());
+
+        let live = data_id(&workspace.join("test.txt")).unwrap();
+        assert!(data_store.get(live).unwrap().is_some());
+        assert_eq!(gc(&data_store, &history_store).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_clean() {
+        let dir = tempdir().unwrap();
+        let (data_store, history_store, workspace) = setup(dir.path());
+        fs::write(workspace.join("test.txt"), "hello").unwrap();
+        save(&data_store, &history_store, &workspace, None).unwrap();
+
+        clean(&data_store, &history_store, &workspace).unwrap();
+
+        assert!(history(&history_store, &workspace).unwrap().is_none());
+        assert!(data_store.keys().unwrap().is_empty());
+    }
+}
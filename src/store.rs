@@ -22,6 +22,10 @@ pub enum StoreError {
     /// An error occurred while persisting a temporary file.
     #[error(transparent)]
     Persist(#[from] tempfile::PersistError),
+
+    /// The compressed data stored for a key failed its integrity check and cannot be trusted.
+    #[error("stored data for key {} is corrupted", .0.digest)]
+    Corrupted(Id),
 }
 
 /// An atomic, content-addressed file store.
@@ -31,15 +35,55 @@ pub enum StoreError {
 pub struct FileStore {
     /// The root directory containing the store's data files.
     directory: PathBuf,
+    /// The zstd compression level applied when writing data into the store.
+    #[serde(default = "default_compression_level")]
+    compression_level: i32,
+}
+
+/// The zstd compression level used when none is specified, deferring to zstd's own default.
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 0;
+
+/// The leading format byte marking data stored as a zstd frame.
+const FORMAT_ZSTD: u8 = 0x01;
+
+/// The leading format byte marking data stored uncompressed.
+const FORMAT_RAW: u8 = 0x02;
+
+/// The number of bytes preceding raw data: its format byte followed by its checksum.
+const RAW_HEADER_LEN: usize = 1 + 8;
+
+/// The magic number opening every zstd frame, used to recognize legacy data written without a format byte.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Computes the integrity checksum guarding raw data, taken from its blake3 digest.
+fn checksum(value: &[u8]) -> [u8; 8] {
+    let mut checksum = [0u8; 8];
+    checksum.copy_from_slice(&blake3::hash(value).as_bytes()[0..8]);
+    checksum
+}
+
+/// Supplies the default compression level when deserializing stores persisted without one.
+fn default_compression_level() -> i32 {
+    DEFAULT_COMPRESSION_LEVEL
 }
 
 impl FileStore {
     /// Initializes a new `FileStore` at the specified path, creating the directory if it does not exist.
     pub fn new(directory: &Path) -> Result<Self, StoreError> {
-        debug!("Initializing FileStore at {:?}", directory);
+        Self::with_compression_level(directory, DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Initializes a new `FileStore` that compresses written data at the given zstd level.
+    /// Lower levels trade storage space for faster writes; reads are unaffected by the level.
+    pub fn with_compression_level(directory: &Path, level: i32) -> Result<Self, StoreError> {
+        debug!(
+            "Initializing FileStore at {:?} with compression level {}",
+            directory, level
+        );
         fs::create_dir_all(directory)?;
         Ok(Self {
             directory: directory.to_path_buf(),
+            compression_level: level,
         })
     }
 
@@ -48,6 +92,11 @@ impl FileStore {
         &self.directory
     }
 
+    /// Returns the zstd compression level applied when writing data into this store.
+    pub fn compression_level(&self) -> i32 {
+        self.compression_level
+    }
+
     /// Constructs the physical file path for a given structural identity.
     fn file_path(&self, key: Id) -> PathBuf {
         let path = self.directory.join(format!("{}.evdata", key.digest));
@@ -56,15 +105,16 @@ impl FileStore {
     }
 
     /// Writes and compresses data into the store for the given key.
+    /// Both compressed and raw data carry a checksum that is verified when it is read back.
+    /// Incompressible data is kept raw whenever compression would not shrink it; a leading format
+    /// byte records which representation was chosen.
     /// Employs a temporary file and atomic rename to ensure partial writes are never observable.
     pub fn set(&self, key: Id, value: &[u8]) -> Result<(), StoreError> {
         let file_path = self.file_path(key);
         debug!("Writing to store: {:?}", file_path);
 
         let mut temp_file = NamedTempFile::new_in(&self.directory)?;
-
-        let compressed_value = zstd::encode_all(Cursor::new(value), 0)?;
-        temp_file.write_all(&compressed_value)?;
+        temp_file.write_all(&self.encode(key, value)?)?;
 
         temp_file.persist(&file_path)?;
         trace!("Successfully persisted data for key {}", key.digest);
@@ -72,19 +122,35 @@ impl FileStore {
         Ok(())
     }
 
+    /// Encodes data into its stored representation, prefixed by the format byte describing it.
+    fn encode(&self, key: Id, value: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let mut encoder = zstd::Encoder::new(vec![FORMAT_ZSTD], self.compression_level)?;
+        encoder.include_checksum(true)?;
+        encoder.write_all(value)?;
+        let compressed_value = encoder.finish()?;
+        if compressed_value.len() < value.len() + RAW_HEADER_LEN {
+            return Ok(compressed_value);
+        }
+
+        trace!("Compression did not shrink key {}, storing raw", key.digest);
+        let mut raw_value = Vec::with_capacity(value.len() + RAW_HEADER_LEN);
+        raw_value.push(FORMAT_RAW);
+        raw_value.extend_from_slice(&checksum(value));
+        raw_value.extend_from_slice(value);
+        Ok(raw_value)
+    }
+
     /// Reads and decompresses data from the store for the given key.
-    /// Returns `None` if the key does not exist.
+    /// Returns `None` if the key does not exist, and `StoreError::Corrupted` if the stored data has an
+    /// unknown format, fails to decode, or does not match its checksum.
     pub fn get(&self, key: Id) -> Result<Option<Vec<u8>>, StoreError> {
         let file_path = self.file_path(key);
         debug!("Reading from store: {:?}", file_path);
         match fs::read(&file_path) {
             Ok(data) => {
-                let decompressed_data = zstd::decode_all(Cursor::new(data))?;
-                trace!(
-                    "Successfully read and decompressed data for key {}",
-                    key.digest
-                );
-                Ok(Some(decompressed_data))
+                let decoded_data = Self::decode(key, data)?;
+                trace!("Successfully read and decoded data for key {}", key.digest);
+                Ok(Some(decoded_data))
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 debug!("File not found in store: {:?}", file_path);
@@ -94,6 +160,28 @@ impl FileStore {
         }
     }
 
+    /// Decodes stored data according to its leading format byte.
+    /// Legacy data written as a bare zstd frame, without a format byte, is still recognized.
+    fn decode(key: Id, data: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+        match data.first() {
+            Some(&FORMAT_ZSTD) => {
+                zstd::decode_all(&data[1..]).map_err(|_| StoreError::Corrupted(key))
+            }
+            Some(&FORMAT_RAW) if data.len() >= RAW_HEADER_LEN => {
+                let (expected, value) = data[1..].split_at(RAW_HEADER_LEN - 1);
+                if checksum(value) != expected {
+                    return Err(StoreError::Corrupted(key));
+                }
+                Ok(value.to_vec())
+            }
+            _ if data.starts_with(&ZSTD_MAGIC) => {
+                trace!("Decoding legacy headerless data for key {}", key.digest);
+                zstd::decode_all(Cursor::new(data)).map_err(|_| StoreError::Corrupted(key))
+            }
+            _ => Err(StoreError::Corrupted(key)),
+        }
+    }
+
     /// Removes a key from the store. Succeeds silently if the key did not exist.
     pub fn remove(&self, key: Id) -> Result<(), StoreError> {
         let file_path = self.file_path(key);
@@ -135,6 +223,24 @@ impl FileStore {
         trace!("Found {} keys in store", keys.len());
         Ok(keys)
     }
+
+    /// Computes the total number of bytes the stored data files occupy on disk.
+    /// Files that disappear while the store is being measured are skipped.
+    pub fn disk_size(&self) -> Result<u64, StoreError> {
+        debug!("Computing on-disk size of FileStore");
+        let mut size = 0;
+        for key in self.keys()? {
+            match fs::metadata(self.file_path(key)) {
+                Ok(metadata) => size += metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    trace!("File for key {} vanished during measurement", key.digest);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        trace!("Store occupies {} bytes on disk", size);
+        Ok(size)
+    }
 }
 
 #[cfg(test)]
@@ -152,6 +258,18 @@ mod tests {
         assert_eq!(store.directory(), store_dir);
     }
 
+    #[test]
+    fn test_with_compression_level() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::with_compression_level(dir.path(), 19).unwrap();
+
+        assert_eq!(store.compression_level(), 19);
+        assert_eq!(
+            FileStore::new(dir.path()).unwrap().compression_level(),
+            DEFAULT_COMPRESSION_LEVEL
+        );
+    }
+
     #[test]
     fn test_directory() {
         let dir = tempdir().unwrap();
@@ -175,15 +293,44 @@ mod tests {
         let dir = tempdir().unwrap();
         let store = FileStore::new(dir.path()).unwrap();
         let id = Id { digest: 12345 };
-        let data = b"test data";
+        let data = b"test data".repeat(64);
 
-        store.set(id, data).unwrap();
+        store.set(id, &data).unwrap();
 
         let file_path = store.file_path(id);
         let read_data = fs::read(file_path).unwrap();
 
-        let expected_compressed_data = zstd::encode_all(Cursor::new(data), 0).unwrap();
-        assert_eq!(read_data, expected_compressed_data);
+        assert_eq!(read_data[0], FORMAT_ZSTD);
+        assert!(read_data.len() < data.len());
+        assert_eq!(zstd::decode_all(&read_data[1..]).unwrap(), data);
+    }
+
+    #[test]
+    fn test_set_incompressible() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let id = Id { digest: 12345 };
+        let mut data = vec![0u8; 64 * 1024];
+        blake3::Hasher::new().finalize_xof().fill(&mut data);
+
+        store.set(id, &data).unwrap();
+
+        let stored = fs::read(store.file_path(id)).unwrap();
+        assert_eq!(stored[0], FORMAT_RAW);
+        assert!(stored.len() <= data.len() + RAW_HEADER_LEN);
+        assert_eq!(store.get(id).unwrap().unwrap(), data);
+    }
+
+    #[test]
+    fn test_set_raw_data_resembling_zstd() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let id = Id { digest: 12345 };
+        let data = ZSTD_MAGIC.to_vec();
+
+        store.set(id, &data).unwrap();
+
+        assert_eq!(store.get(id).unwrap().unwrap(), data);
     }
 
     #[test]
@@ -193,8 +340,7 @@ mod tests {
         let id = Id { digest: 12345 };
         let data = b"test data";
 
-        let compressed_data = zstd::encode_all(Cursor::new(data), 0).unwrap();
-        fs::write(store.file_path(id), compressed_data).unwrap();
+        store.set(id, data).unwrap();
 
         let read_data = store.get(id).unwrap().unwrap();
         assert_eq!(read_data, data);
@@ -204,6 +350,73 @@ mod tests {
         assert!(missing_data.is_none());
     }
 
+    #[test]
+    fn test_get_legacy() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let id = Id { digest: 12345 };
+        let data = b"test data";
+
+        let compressed_data = zstd::encode_all(Cursor::new(data), 0).unwrap();
+        fs::write(store.file_path(id), compressed_data).unwrap();
+
+        assert_eq!(store.get(id).unwrap().unwrap(), data);
+    }
+
+    #[test]
+    fn test_get_across_compression_levels() {
+        let dir = tempdir().unwrap();
+        let id = Id { digest: 12345 };
+        let data = b"test data test data test data".repeat(64);
+
+        for level in [1, DEFAULT_COMPRESSION_LEVEL, 9, 19] {
+            let store = FileStore::with_compression_level(dir.path(), level).unwrap();
+            store.set(id, &data).unwrap();
+
+            let reader = FileStore::new(dir.path()).unwrap();
+            assert_eq!(reader.get(id).unwrap().unwrap(), data);
+        }
+    }
+
+    /// Flips every bit of the stored byte at `position` for the given key.
+    fn flip_byte(store: &FileStore, id: Id, position: usize) {
+        let file_path = store.file_path(id);
+        let mut stored = fs::read(&file_path).unwrap();
+        stored[position] ^= 0xFF;
+        fs::write(&file_path, stored).unwrap();
+    }
+
+    #[test]
+    fn test_get_corrupted() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let id = Id { digest: 12345 };
+        let data = b"test data".repeat(64);
+
+        store.set(id, &data).unwrap();
+        let last = fs::metadata(store.file_path(id)).unwrap().len() as usize - 1;
+        flip_byte(&store, id, last);
+        assert!(matches!(store.get(id), Err(StoreError::Corrupted(key)) if key == id));
+
+        store.set(id, &data).unwrap();
+        flip_byte(&store, id, 0);
+        assert!(matches!(store.get(id), Err(StoreError::Corrupted(key)) if key == id));
+    }
+
+    #[test]
+    fn test_get_corrupted_raw() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let id = Id { digest: 12345 };
+        let data = b"test data";
+
+        store.set(id, data).unwrap();
+        assert_eq!(fs::read(store.file_path(id)).unwrap()[0], FORMAT_RAW);
+
+        flip_byte(&store, id, RAW_HEADER_LEN);
+        assert!(matches!(store.get(id), Err(StoreError::Corrupted(key)) if key == id));
+    }
+
     #[test]
     fn test_remove() {
         let dir = tempdir().unwrap();
@@ -236,4 +449,22 @@ mod tests {
 
         assert_eq!(keys, vec![id1, id2]);
     }
+
+    #[test]
+    fn test_disk_size() {
+        let dir = tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        assert_eq!(store.disk_size().unwrap(), 0);
+
+        let id1 = Id { digest: 12345 };
+        let id2 = Id { digest: 67890 };
+        store.set(id1, b"data1").unwrap();
+        store.set(id2, &b"data2".repeat(64)).unwrap();
+
+        let expected: u64 = [id1, id2]
+            .iter()
+            .map(|id| fs::metadata(store.file_path(*id)).unwrap().len())
+            .sum();
+        assert_eq!(store.disk_size().unwrap(), expected);
+    }
 }